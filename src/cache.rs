@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::parser::parse_log;
+use crate::report::BuildReport;
+
+struct LogFile {
+    report: BuildReport,
+    modified: SystemTime,
+}
+
+/// Caches parsed [`BuildReport`]s per log-file path, together with the file's
+/// last-modified timestamp, so a tool polling a log during a watched or
+/// continuous LaTeX build can skip reparsing a log that hasn't changed
+/// since it was last read.
+#[derive(Default)]
+pub struct ReportCache {
+    entries: HashMap<PathBuf, LogFile>,
+}
+
+impl ReportCache {
+    pub fn new() -> ReportCache {
+        ReportCache {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Re-read and re-parse `path` if it hasn't been parsed before or its
+    /// on-disk modification time is newer than the last parse. Returns
+    /// whether the report was (re)parsed.
+    pub fn update<P: AsRef<Path>>(&mut self, path: P) -> io::Result<bool> {
+        let path = path.as_ref();
+        let modified = path.metadata()?.modified()?;
+
+        let up_to_date = self
+            .entries
+            .get(path)
+            .is_some_and(|entry| entry.modified >= modified);
+        if up_to_date {
+            return Ok(false);
+        }
+
+        let report = parse_log(File::open(path)?);
+        self.entries
+            .insert(path.to_owned(), LogFile { report, modified });
+
+        Ok(true)
+    }
+
+    /// The most recently parsed report for `path`, if `update` has parsed it
+    /// at least once.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&BuildReport> {
+        self.entries.get(path.as_ref()).map(|entry| &entry.report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_log_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("texoutparse-rs-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_update_parses_new_file_then_skips_unchanged() {
+        let path = temp_log_path("update.log");
+        std::fs::write(&path, "! Undefined control sequence.\n").unwrap();
+
+        let mut cache = ReportCache::new();
+        assert!(cache.update(&path).unwrap());
+        assert!(!cache.update(&path).unwrap());
+
+        let report = cache.get(&path).unwrap();
+        assert_eq!(report.errors, 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_update_reparses_after_modification() {
+        let path = temp_log_path("modify.log");
+        std::fs::write(&path, "! Undefined control sequence.\n").unwrap();
+
+        let mut cache = ReportCache::new();
+        assert!(cache.update(&path).unwrap());
+
+        // Ensure the new modification time is observably later.
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"LaTeX Warning: Draft mode on.\n").unwrap();
+        drop(file);
+
+        assert!(cache.update(&path).unwrap());
+        assert_eq!(cache.get(&path).unwrap().warnings, 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_get_before_update_is_none() {
+        let cache = ReportCache::new();
+        assert!(cache.get("/nonexistent/path.log").is_none());
+    }
+}
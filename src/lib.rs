@@ -5,4 +5,6 @@ pub mod report;
 pub use report::*;
 pub mod parser;
 pub use parser::*;
+pub mod cache;
+pub use cache::*;
 
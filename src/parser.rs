@@ -30,8 +30,41 @@ lazy_static! {
     ).unwrap();
 
     static ref MISSING_REFERENCE: Regex = Regex::new(
-        r#"^(Citation|Reference) `([^']+)' on page \d+ undefined on input line \d+."#
+        r#"^(Citation|Reference) `([^']+)' on page \d+ undefined on input line (\d+)."#
     ).unwrap();
+
+    static ref FILE_PATH: Regex = Regex::new(
+        r#"^[^()\s]+\.(?:tex|sty|cls|def|ltx)"#
+    ).unwrap();
+
+    static ref ERROR_LINE: Regex = Regex::new(r#"^l\.(\d+)(.*)$"#).unwrap();
+}
+
+/// TeX's default `max_print_line`: the column at which the log is hard-wrapped.
+pub const DEFAULT_WRAP_WIDTH: usize = 79;
+
+/// How many lines of context to keep after each kind of message,
+/// so that e.g. errors (which are usually more worth inspecting) can be
+/// given more surrounding context than info messages. Lines are counted
+/// after hard-wrapped lines have been rejoined, so a log line wrapped over
+/// several physical lines counts (and is stored) as one context line.
+#[derive(Debug, Clone, Copy)]
+pub struct ContextLines {
+    pub error: usize,
+    pub warning: usize,
+    pub badbox: usize,
+    pub info: usize,
+}
+
+impl Default for ContextLines {
+    fn default() -> ContextLines {
+        ContextLines {
+            error: 3,
+            warning: 2,
+            badbox: 1,
+            info: 0,
+        }
+    }
 }
 
 struct LogParser<'a, B: 'a + BufRead> {
@@ -39,7 +72,10 @@ struct LogParser<'a, B: 'a + BufRead> {
     reader: B,
     lineno: usize,
     collect_remaining: usize,
-    context_lines: usize,
+    context_lines: ContextLines,
+    wrap_width: usize,
+    file_stack: Vec<Option<String>>,
+    pending_line: Option<String>,
 }
 
 impl<'a, B: 'a + BufRead> LogParser<'a, B> {
@@ -55,24 +91,173 @@ impl<'a, B: 'a + BufRead> LogParser<'a, B> {
         }
     }
 
-    fn after_match(&mut self) {
-        self.collect_remaining = self.context_lines;
+    /// Strip the trailing `\n`/`\r\n` from a physical line, returning its content.
+    fn content_without_eol(line: &str) -> &str {
+        line.trim_end_matches(['\n', '\r'])
+    }
+
+    /// Reconstruct a logical line from one or more physical lines.
+    ///
+    /// TeX hard-wraps its log output at `max_print_line` (79 columns by
+    /// default), so a single logical message can be split across several
+    /// physical lines. Whenever a physical line's content is exactly
+    /// `wrap_width` characters long, it is assumed to continue on the next
+    /// physical line and the two are joined with no separator. This
+    /// is a heuristic: a genuinely `wrap_width`-character line that really
+    /// does end there will still be joined with whatever follows, but the
+    /// `(component)` continuation rule in `parse` is applied afterwards and
+    /// is unaffected by this heuristic.
+    fn next_logical_line(&mut self) -> Option<String> {
+        if let Some(line) = self.pending_line.take() {
+            return Some(line);
+        }
+
+        let first = self.next_line()?;
+        let mut logical = Self::content_without_eol(&first).to_owned();
+        let mut last_len = logical.chars().count();
+
+        while last_len == self.wrap_width {
+            match self.next_line() {
+                Some(next) => {
+                    let content = Self::content_without_eol(&next);
+                    last_len = content.chars().count();
+                    logical.push_str(content);
+                }
+                None => break,
+            }
+        }
+
+        Some(logical)
+    }
+
+    /// Update the parenthesis file stack from a logical line.
+    ///
+    /// TeX delimits file scopes with balanced parentheses, e.g.
+    /// `(./chapter1.tex ... )`, possibly nested many levels deep, and not
+    /// every `(`/`)` pair in the log opens or closes a file (parentheses used
+    /// for other grouping purposes show up too). Every `(` pushes a stack frame
+    /// so that depth tracking stays correct; the frame records a file path
+    /// only when it is immediately followed by something that looks like
+    /// one. Every `)` pops a frame, clamping at empty rather than
+    /// underflowing, since parentheses can appear unbalanced within the
+    /// free-text part of messages.
+    fn update_file_stack(&mut self, line: &str) {
+        for (i, c) in line.char_indices() {
+            match c {
+                '(' => {
+                    let rest = &line[i + 1..];
+                    let file = FILE_PATH.find(rest).map(|m| m.as_str().to_owned());
+                    self.file_stack.push(file);
+                }
+                ')' => {
+                    self.file_stack.pop();
+                }
+                _ => {}
+            }
+        }
     }
 
-    fn parse_line(&mut self, line: &str) {
+    fn current_file(&self) -> Option<String> {
+        self.file_stack.iter().rev().find_map(|f| f.clone())
+    }
+
+    /// Put a logical line back so the next call to `next_logical_line`
+    /// returns it again, for the one-line lookahead `process_error` needs.
+    fn push_back(&mut self, line: String) {
+        self.pending_line = Some(line);
+    }
+
+    /// After a TeX error (`! ...`), look for the `l.<n>` line TeX prints to
+    /// pinpoint the offending input line, e.g.:
+    ///
+    /// ```text
+    /// ! Undefined control sequence.
+    /// l.7 \foo
+    ///         bar
+    /// ```
+    ///
+    /// The line number goes into `details["line"]`, and the snippet either
+    /// side of the error point - "before" on the `l.N` line itself, "after"
+    /// on the line below, aligned under where "before" left off - goes into
+    /// `details["before"]`/`details["after"]`.
+    ///
+    /// LaTeX commonly interposes one or more help-text lines (e.g. "See the
+    /// LaTeX manual...", "Type H <return> for immediate help.") and blank
+    /// lines between the `! ...` message and `l.N`, so lines are consumed
+    /// and discarded until `l.N` is found. If a line is reached that itself
+    /// starts a new top-level message, the search gives up and puts that
+    /// line back unconsumed rather than swallowing it as help text.
+    fn capture_error_line_hint(&mut self, info: &mut MessageInfo) {
+        loop {
+            let line = match self.next_logical_line() {
+                Some(line) => line,
+                None => return,
+            };
+
+            let m = match ERROR_LINE.captures(&line) {
+                Some(m) => m,
+                None => {
+                    if Self::starts_new_message(&line) {
+                        self.push_back(line);
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            info.details.insert(
+                String::from("line"),
+                m.get(1).unwrap().as_str().to_owned(),
+            );
+            info.details.insert(
+                String::from("before"),
+                m.get(2).unwrap().as_str().trim_start().to_owned(),
+            );
+
+            if let Some(after) = self.next_logical_line() {
+                info.details
+                    .insert(String::from("after"), after.trim_start().to_owned());
+            }
+
+            return;
+        }
+    }
+
+    /// Whether `line` itself starts a new top-level message, so a lookahead
+    /// like [`capture_error_line_hint`] knows to stop searching rather than
+    /// swallow unrelated content.
+    fn starts_new_message(line: &str) -> bool {
+        ERROR.is_match(line)
+            || WARNING.is_match(line)
+            || INFO.is_match(line)
+            || BADBOX.is_match(line)
+    }
+
+    fn after_match(&mut self, context_lines: usize) {
+        self.collect_remaining = context_lines;
+    }
+
+    /// Try to match `line` against a message pattern. Returns whether a
+    /// match was found, so that `parse` can tell a genuine new message
+    /// apart from a context line to collect.
+    fn parse_line(&mut self, line: &str) -> bool {
         if let Some(m) = INFO.captures(&line) {
             self.process_info(m);
-            //self.after_match();
+            self.after_match(self.context_lines.info);
         } else if let Some(m) = BADBOX.captures(&line) {
             self.process_badbox(m);
-            //self.after_match();
+            self.after_match(self.context_lines.badbox);
         } else if let Some(m) = WARNING.captures(&line) {
             self.process_warning(m);
-            //self.after_match();
+            self.after_match(self.context_lines.warning);
         } else if let Some(m) = ERROR.captures(&line) {
             self.process_error(m);
-            //self.after_match();
+            self.after_match(self.context_lines.error);
+        } else {
+            return false;
         }
+
+        true
     }
 
     fn process_generic(&mut self, m: Captures) -> MessageInfo {
@@ -80,6 +265,7 @@ impl<'a, B: 'a + BufRead> LogParser<'a, B> {
             full: m.get(0).unwrap().as_str().to_owned(),
             details: HashMap::new(),
             context_lines: Vec::new(),
+            source_file: self.current_file(),
         };
 
         // 0 - Whole match
@@ -124,6 +310,7 @@ impl<'a, B: 'a + BufRead> LogParser<'a, B> {
             full: m.get(0).unwrap().as_str().to_owned(),
             details: HashMap::new(),
             context_lines: Vec::new(),
+            source_file: self.current_file(),
         };
 
         // Regex match groups
@@ -175,17 +362,17 @@ impl<'a, B: 'a + BufRead> LogParser<'a, B> {
     }
 
 
-    fn process_missing_reference(&mut self, label: &str) {
+    fn process_missing_reference(&mut self, label: &str, line: usize) {
         self.report.missing_references += 1;
         self.report.messages.push(
-            Message::MissingReference {label: label.to_owned()}
+            Message::MissingReference {label: label.to_owned(), line}
         )
     }
 
-    fn process_missing_citation(&mut self, label: &str) {
+    fn process_missing_citation(&mut self, label: &str, line: usize) {
         self.report.missing_citations += 1;
         self.report.messages.push(
-            Message::MissingCitation {label: label.to_owned()}
+            Message::MissingCitation {label: label.to_owned(), line}
         )
     }
 
@@ -196,11 +383,13 @@ impl<'a, B: 'a + BufRead> LogParser<'a, B> {
                 // 0 - whole match
                 // 1 - type
                 // 2 - label
+                // 3 - input line
                 let type_ = m.get(1).unwrap().as_str();
+                let line = m.get(3).unwrap().as_str().parse().unwrap_or(0);
                 if type_ == "Citation" {
-                    self.process_missing_citation(m.get(2).unwrap().as_str());
+                    self.process_missing_citation(m.get(2).unwrap().as_str(), line);
                 } else if type_ == "Reference" {
-                    self.process_missing_reference(m.get(2).unwrap().as_str());
+                    self.process_missing_reference(m.get(2).unwrap().as_str(), line);
                 }
                 return
             }
@@ -215,14 +404,17 @@ impl<'a, B: 'a + BufRead> LogParser<'a, B> {
                 full: m.get(0).unwrap().as_str().to_owned(),
                 details: HashMap::new(),
                 context_lines: Vec::new(),
+                source_file: self.current_file(),
             };
 
             info.details
                 .insert(String::from("message"), message.as_str().to_owned());
+            self.capture_error_line_hint(&mut info);
             self.report.errors += 1;
             self.report.messages.push(Message::Error(info))
         } else {
-            let info = self.process_generic(m);
+            let mut info = self.process_generic(m);
+            self.capture_error_line_hint(&mut info);
             self.report.errors += 1;
             self.report.messages.push(Message::Error(info))
         }
@@ -230,18 +422,27 @@ impl<'a, B: 'a + BufRead> LogParser<'a, B> {
 }
 
 impl<'a, B: 'a + BufRead> LogParser<'a, B> {
-    pub fn new(report: &'a mut BuildReport, reader: B, context_lines: usize) -> LogParser<'a, B> {
+    pub fn new(report: &'a mut BuildReport, reader: B, context_lines: ContextLines) -> LogParser<'a, B> {
         LogParser {
             report,
             reader,
             lineno: 0,
             collect_remaining: 0,
             context_lines,
+            wrap_width: DEFAULT_WRAP_WIDTH,
+            file_stack: Vec::new(),
+            pending_line: None,
         }
     }
 
+    pub fn with_wrap_width(mut self, wrap_width: usize) -> LogParser<'a, B> {
+        self.wrap_width = wrap_width;
+        self
+    }
+
     pub fn parse(mut self) {
-        while let Some(line) = self.next_line() {
+        while let Some(line) = self.next_logical_line() {
+            self.update_file_stack(&line);
 
             if let Some(last) = self.report.messages.last_mut() {
                 if let Some(cmpt) = last.get_component_name() {
@@ -253,24 +454,47 @@ impl<'a, B: 'a + BufRead> LogParser<'a, B> {
                         continue;
                     }
                 }
+            }
 
-                //if self.collect_remaining > 0 {
-                //    last.add_context(line);
-                //    self.collect_remaining -= 1;
-                //    continue;
-                //}
+            // A line that starts a new message always takes priority over
+            // collecting it as context for the previous one.
+            if self.parse_line(&line) {
+                continue;
             }
 
-            self.parse_line(&line);
+            if self.collect_remaining > 0 {
+                if let Some(last) = self.report.messages.last_mut() {
+                    last.add_context(line);
+                }
+                self.collect_remaining -= 1;
+            }
         }
     }
 }
 
 pub fn parse_log<R: Read>(log: R) -> BuildReport {
+    parse_log_with_wrap_width(log, DEFAULT_WRAP_WIDTH)
+}
+
+/// Parse a TeX log, unwrapping lines hard-wrapped at `wrap_width` columns
+/// before matching. Use this instead of [`parse_log`] when the log was
+/// produced with a non-default `max_print_line`.
+pub fn parse_log_with_wrap_width<R: Read>(log: R, wrap_width: usize) -> BuildReport {
+    parse_log_with_options(log, wrap_width, ContextLines::default())
+}
+
+/// Parse a TeX log with full control over line-unwrapping and how much
+/// context to keep around each message.
+pub fn parse_log_with_options<R: Read>(
+    log: R,
+    wrap_width: usize,
+    context_lines: ContextLines,
+) -> BuildReport {
     let reader = BufReader::new(log);
     let mut report = BuildReport::new();
 
-    let parser: LogParser<BufReader<R>> = LogParser::new(&mut report, reader, 2);
+    let parser: LogParser<BufReader<R>> =
+        LogParser::new(&mut report, reader, context_lines).with_wrap_width(wrap_width);
 
     parser.parse();
 
@@ -285,7 +509,7 @@ mod tests {
         let mut cursor = io::Cursor::new(&line);
         let mut reader = BufReader::new(cursor);
         let mut report = BuildReport::new();
-        let mut parser = LogParser::new(&mut report, reader, 2);
+        let mut parser = LogParser::new(&mut report, reader, ContextLines::default());
         parser.parse_line(&line);
         report
     }
@@ -466,6 +690,146 @@ mod tests {
         
     }
     
+    #[test]
+    fn test_wrapped_line_is_reassembled() {
+        let line1 = "Package hyperref Warning: Draft mode on, but this sentence is long enough to wr";
+        let line2 = "ap across the output width for testing purposes here\n";
+        assert_eq!(line1.len(), DEFAULT_WRAP_WIDTH);
+
+        let log = format!("{}\n{}", line1, line2);
+        let report = parse_log(io::Cursor::new(log));
+
+        assert_eq!(report.warnings, 1);
+        if let Message::Warning(info) = report.messages.get(0).unwrap() {
+            assert_eq!(
+                info.details.get("message").unwrap(),
+                "Draft mode on, but this sentence is long enough to wrap across the output width for testing purposes here"
+            );
+        } else {
+            panic!("expected a warning message");
+        }
+    }
+
+    #[test]
+    fn test_wrapped_line_with_multibyte_chars_is_reassembled() {
+        let line1 = "Package hyperref Warning: Ma\u{ef}trise du mode brouillon activ\u{e9}e pour ce document i";
+        let line2 = "ci\n";
+        assert_eq!(line1.chars().count(), DEFAULT_WRAP_WIDTH);
+        assert!(line1.len() > DEFAULT_WRAP_WIDTH);
+
+        let log = format!("{}\n{}", line1, line2);
+        let report = parse_log(io::Cursor::new(log));
+
+        assert_eq!(report.warnings, 1);
+        if let Message::Warning(info) = report.messages.get(0).unwrap() {
+            assert_eq!(
+                info.details.get("message").unwrap(),
+                "Ma\u{ef}trise du mode brouillon activ\u{e9}e pour ce document ici"
+            );
+        } else {
+            panic!("expected a warning message");
+        }
+    }
+
+    #[test]
+    fn test_source_file_tracked_from_paren_stack() {
+        let log = "(./chapter1.tex\nLaTeX Warning: Draft mode on.\n)\nLaTeX Warning: Draft mode on.\n";
+        let report = parse_log(io::Cursor::new(log));
+
+        assert_eq!(report.warnings, 2);
+        let inside = report.messages.get(0).unwrap().as_ref().unwrap();
+        assert_eq!(inside.source_file.as_deref(), Some("./chapter1.tex"));
+
+        let outside = report.messages.get(1).unwrap().as_ref().unwrap();
+        assert_eq!(outside.source_file, None);
+    }
+
+    #[test]
+    fn test_error_captures_line_hint() {
+        let log = "! Undefined control sequence.\nl.7 \\foo\n        bar\n";
+        let report = parse_log(io::Cursor::new(log));
+
+        assert_eq!(report.errors, 1);
+        if let Message::Error(info) = report.messages.get(0).unwrap() {
+            assert_eq!(info.details.get("line").unwrap(), "7");
+            assert_eq!(info.details.get("before").unwrap(), "\\foo");
+            assert_eq!(info.details.get("after").unwrap(), "bar");
+        } else {
+            panic!("expected an error message");
+        }
+    }
+
+    #[test]
+    fn test_error_captures_line_hint_past_help_text() {
+        let log = "! LaTeX Error: \\begin{document} not allowed here.\nSee the LaTeX manual or LaTeX Companion for explanation.\nType H <return> for immediate help.\n \nl.7 \\begin{document}\n             \n";
+        let report = parse_log(io::Cursor::new(log));
+
+        assert_eq!(report.errors, 1);
+        if let Message::Error(info) = report.messages.get(0).unwrap() {
+            assert_eq!(info.details.get("line").unwrap(), "7");
+            assert_eq!(info.details.get("before").unwrap(), "\\begin{document}");
+        } else {
+            panic!("expected an error message");
+        }
+    }
+
+    #[test]
+    fn test_error_line_hint_gives_up_at_next_message() {
+        let log = "! Undefined control sequence.\nLaTeX Warning: Reference `not present' on page 1 undefined on input line 12.\n";
+        let report = parse_log(io::Cursor::new(log));
+
+        assert_eq!(report.errors, 1);
+        assert_eq!(report.missing_references, 1);
+        let info = report.messages.get(0).unwrap().as_ref().unwrap();
+        assert!(!info.details.contains_key("line"));
+    }
+
+    #[test]
+    fn test_to_diagnostics() {
+        let log = "! Undefined control sequence.\nl.7 \\foo\n        bar\nLaTeX Warning: Reference `not present' on page 1 undefined on input line 12.\n";
+        let report = parse_log(io::Cursor::new(log));
+        let diagnostics = report.to_diagnostics();
+
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].line, Some(7));
+
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].line, Some(12));
+        assert_eq!(diagnostics[1].message, "Missing reference: not present");
+    }
+
+    #[test]
+    fn test_context_lines_collected_after_match() {
+        let log = "! Undefined control sequence.\nl.7 \\foo\n        bar\ncontext one\ncontext two\ncontext three\n";
+        let context_lines = ContextLines {
+            error: 2,
+            ..ContextLines::default()
+        };
+        let report = parse_log_with_options(io::Cursor::new(log), DEFAULT_WRAP_WIDTH, context_lines);
+
+        assert_eq!(report.errors, 1);
+        let info = report.messages.get(0).unwrap().as_ref().unwrap();
+        assert_eq!(info.context_lines, vec!["context one", "context two"]);
+    }
+
+    #[test]
+    fn test_new_message_takes_priority_over_context_collection() {
+        let log = "! Undefined control sequence.\nl.7 \\foo\n        bar\nUnderfull \\hbox (badness 10000) detected at line 12\n";
+        let context_lines = ContextLines {
+            error: 3,
+            ..ContextLines::default()
+        };
+        let report = parse_log_with_options(io::Cursor::new(log), DEFAULT_WRAP_WIDTH, context_lines);
+
+        assert_eq!(report.errors, 1);
+        assert_eq!(report.badboxes, 1);
+
+        let error = report.messages.get(0).unwrap().as_ref().unwrap();
+        assert!(error.context_lines.is_empty());
+    }
+
     #[test]
     fn test_underfull_vbox_has_occurred_with_page() {
         let line = "Underfull \\vbox (badness 10000) has occurred while \\output is active [38]";
@@ -1,12 +1,22 @@
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::fmt;
 
+lazy_static! {
+    static ref ON_INPUT_LINE: Regex = Regex::new(r#"on input line (\d+)"#).unwrap();
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct MessageInfo {
     pub full: String,
     pub details: HashMap<String, String>,
     pub context_lines: Vec<String>,
+    /// The `.tex`/`.sty`/`.cls`/... file open when this message was emitted,
+    /// as tracked by the parenthesis file stack. `None` if no recognised
+    /// input file is open at this point.
+    pub source_file: Option<String>,
 }
 
 impl MessageInfo {
@@ -42,8 +52,8 @@ pub enum Message {
     Warning(MessageInfo),
     Badbox(MessageInfo),
     Info(MessageInfo),
-    MissingCitation { label: String },
-    MissingReference { label: String },
+    MissingCitation { label: String, line: usize },
+    MissingReference { label: String, line: usize },
 }
 
 use Message::*;
@@ -95,12 +105,82 @@ impl Message {
             Warning(ref inner) => inner.full.clone(),
             Info(ref inner) => inner.full.clone(),
             Badbox(ref inner) => inner.full.clone(),
-            MissingCitation { label } => format!("Missing citation: {}", &label),
-            MissingReference { label } => format!("Missing reference: {}", &label),
+            MissingCitation { label, .. } => format!("Missing citation: {}", &label),
+            MissingReference { label, .. } => format!("Missing reference: {}", &label),
+        }
+    }
+
+    /// Find the `on input line <n>.` suffix TeX appends to many warnings and
+    /// info messages, used to surface a line number for these even though
+    /// they aren't captured into a `details` key the way badboxes/errors are.
+    fn input_line(inner: &MessageInfo) -> Option<usize> {
+        ON_INPUT_LINE
+            .captures(&inner.full)
+            .and_then(|m| m.get(1))
+            .and_then(|m| m.as_str().parse().ok())
+    }
+
+    /// Convert this message into an editor-ready [`Diagnostic`].
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let message = self.to_str();
+        match self {
+            Error(inner) => Diagnostic {
+                file: inner.source_file.clone(),
+                line: inner.details.get("line").and_then(|l| l.parse().ok()),
+                severity: Severity::Error,
+                message,
+            },
+            Warning(inner) => Diagnostic {
+                file: inner.source_file.clone(),
+                line: Self::input_line(inner),
+                severity: Severity::Warning,
+                message,
+            },
+            Info(inner) => Diagnostic {
+                file: inner.source_file.clone(),
+                line: Self::input_line(inner),
+                severity: Severity::Information,
+                message,
+            },
+            Badbox(inner) => Diagnostic {
+                file: inner.source_file.clone(),
+                line: inner
+                    .details
+                    .get("line")
+                    .or_else(|| inner.details.get("start_line"))
+                    .and_then(|l| l.parse().ok()),
+                severity: Severity::Warning,
+                message,
+            },
+            MissingCitation { line, .. } | MissingReference { line, .. } => Diagnostic {
+                file: None,
+                line: Some(*line),
+                severity: Severity::Warning,
+                message,
+            },
         }
     }
 }
 
+/// Severity of a [`Diagnostic`], matching the LSP `DiagnosticSeverity` levels
+/// this crate's messages map onto.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+}
+
+/// An editor-ready diagnostic derived from a [`Message`], in the shape tools
+/// like `texlab` use for `textDocument/publishDiagnostics`.
+#[derive(Debug, Serialize, Clone)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct BuildReport {
     pub errors: usize,
@@ -124,6 +204,11 @@ impl BuildReport {
             missing_references: 0,
         }
     }
+
+    /// Flatten this report's messages into editor-ready diagnostics.
+    pub fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        self.messages.iter().map(Message::to_diagnostic).collect()
+    }
 }
 
 impl fmt::Display for BuildReport {